@@ -0,0 +1,134 @@
+use crate::sources::SourceKind;
+use log::warn;
+use serde::Deserialize;
+use std::{fs, io};
+
+fn default_poll_interval_secs() -> u64 {
+    3600
+}
+
+fn default_notify_icon() -> String {
+    "librewolf".to_string()
+}
+
+/// Daemon configuration, either loaded from a `--config path.toml` file or
+/// built from the `--repos` shorthand via [`from_repos_flag`].
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+    #[serde(default = "default_notify_icon")]
+    pub notify_icon: String,
+    #[serde(rename = "repo", default)]
+    pub repos: Vec<RepoConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RepoConfig {
+    pub name: String,
+    #[serde(default)]
+    pub source: SourceKind,
+    #[serde(default)]
+    pub include_prereleases: bool,
+    /// Overrides `poll_interval_secs` for this repo only.
+    pub interval: Option<u64>,
+    /// When set, download a matching release asset on a new release, in
+    /// addition to firing the desktop notification.
+    pub download: Option<DownloadConfig>,
+    /// Required when `source = "gitea"`: the Gitea/Forgejo host, e.g.
+    /// `git.example.org`.
+    pub host: Option<String>,
+    /// Token sent as `Authorization: token <token>` to `host`, for private
+    /// repos on a self-hosted Gitea/Forgejo instance.
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DownloadConfig {
+    /// Glob matched against each release asset's file name, e.g.
+    /// `*-x86_64-unknown-linux-gnu.tar.gz`. May contain `{os}`/`{arch}`
+    /// placeholders, substituted with the current `std::env::consts::OS`/
+    /// `ARCH` before matching, to target the running platform without
+    /// hardcoding a triple.
+    pub asset_pattern: String,
+    /// Directory the matched asset is downloaded into.
+    pub dest: String,
+}
+
+pub fn load_config(path: &str) -> Result<Config, io::Error> {
+    let content = fs::read_to_string(path)?;
+    let config: Config =
+        toml::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    for repo in &config.repos {
+        if repo.source == SourceKind::Gitea && repo.host.as_deref().unwrap_or_default().is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("repo \"{}\" has source = \"gitea\" but no host", repo.name),
+            ));
+        }
+    }
+
+    Ok(config)
+}
+
+/// Builds the same in-memory shape as a TOML config from the legacy
+/// comma-separated `--repos` flag, so both ways of configuring repos feed
+/// the same daemon loop.
+pub fn from_repos_flag(repos: &str, include_prereleases: bool) -> Config {
+    let repos = repos
+        .trim()
+        .split(',')
+        .filter_map(|entry| {
+            let entry = entry.trim();
+            if let Some(name) = entry.strip_prefix("crates:") {
+                Some(RepoConfig {
+                    name: name.to_string(),
+                    source: SourceKind::CratesIo,
+                    include_prereleases,
+                    interval: None,
+                    download: None,
+                    host: None,
+                    token: None,
+                })
+            } else if let Some(rest) = entry.strip_prefix("gitea:") {
+                let (host, name) = match rest.split_once('/') {
+                    Some((host, name)) if !host.is_empty() => (host, name),
+                    _ => {
+                        warn!(
+                            "Skipping {:?}: gitea entries need a host, e.g. gitea:git.example.org/owner/repo",
+                            entry
+                        );
+                        return None;
+                    }
+                };
+                Some(RepoConfig {
+                    name: name.to_string(),
+                    source: SourceKind::Gitea,
+                    include_prereleases,
+                    interval: None,
+                    download: None,
+                    host: Some(host.to_string()),
+                    token: None,
+                })
+            } else {
+                let name = entry.strip_prefix("github:").unwrap_or(entry);
+                Some(RepoConfig {
+                    name: name.to_string(),
+                    source: SourceKind::Github,
+                    include_prereleases,
+                    interval: None,
+                    download: None,
+                    host: None,
+                    token: None,
+                })
+            }
+        })
+        .collect();
+
+    Config {
+        poll_interval_secs: default_poll_interval_secs(),
+        notify_icon: default_notify_icon(),
+        repos,
+    }
+}
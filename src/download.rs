@@ -0,0 +1,101 @@
+use futures_util::StreamExt;
+use glob::Pattern;
+use log::{error, info, warn};
+use reqwest::Client;
+use tokio::io::AsyncWriteExt;
+
+use crate::config::DownloadConfig;
+use crate::sources::Asset;
+
+/// Downloads the first asset matching `config.asset_pattern` into
+/// `config.dest`, logging (rather than propagating) any failure, since a
+/// failed download shouldn't stop the daemon from watching other repos.
+/// Any `{os}`/`{arch}` placeholders in the pattern are substituted with
+/// `std::env::consts::OS`/`ARCH` before matching, so a single config entry
+/// can target whichever platform the daemon is currently running on.
+pub async fn download_release(
+    client: &Client,
+    config: &DownloadConfig,
+    assets: &[Asset],
+    repo_key: &str,
+) {
+    let pattern_str = config
+        .asset_pattern
+        .replace("{os}", std::env::consts::OS)
+        .replace("{arch}", std::env::consts::ARCH);
+    let pattern = match Pattern::new(&pattern_str) {
+        Ok(pattern) => pattern,
+        Err(e) => {
+            error!("Invalid asset_pattern for {}: {}", repo_key, e);
+            return;
+        }
+    };
+
+    let asset = match assets.iter().find(|asset| pattern.matches(&asset.name)) {
+        Some(asset) => asset,
+        None => {
+            warn!(
+                "No release asset matching {:?} for {}",
+                config.asset_pattern, repo_key
+            );
+            return;
+        }
+    };
+
+    if let Err(e) = tokio::fs::create_dir_all(&config.dest).await {
+        error!("Failed to create destination dir for {}: {}", repo_key, e);
+        return;
+    }
+    let dest_path = std::path::Path::new(&config.dest).join(&asset.name);
+
+    let resp = match client
+        .get(&asset.url)
+        .header("User-Agent", "curl/8.11.0")
+        .send()
+        .await
+    {
+        Ok(resp) => resp,
+        Err(e) => {
+            error!("Failed to download {} for {}: {}", asset.name, repo_key, e);
+            return;
+        }
+    };
+
+    let mut file = match tokio::fs::File::create(&dest_path).await {
+        Ok(file) => file,
+        Err(e) => {
+            error!("Failed to create {:?}: {}", dest_path, e);
+            return;
+        }
+    };
+
+    let mut downloaded: u64 = 0;
+    let mut stream = resp.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                error!("Error streaming {} for {}: {}", asset.name, repo_key, e);
+                let _ = tokio::fs::remove_file(&dest_path).await;
+                return;
+            }
+        };
+        if let Err(e) = file.write_all(&chunk).await {
+            error!("Error writing {:?}: {}", dest_path, e);
+            let _ = tokio::fs::remove_file(&dest_path).await;
+            return;
+        }
+        downloaded += chunk.len() as u64;
+    }
+
+    if downloaded != asset.size {
+        error!(
+            "Downloaded size {} does not match expected size {} for {}, removing {:?}",
+            downloaded, asset.size, asset.name, dest_path
+        );
+        let _ = tokio::fs::remove_file(&dest_path).await;
+        return;
+    }
+
+    info!("Downloaded {} ({} bytes) to {:?}", asset.name, downloaded, dest_path);
+}
@@ -1,8 +1,10 @@
-use log::{error, info, warn};
+use log::{error, info};
 use notify_rust::Notification;
 use std::{
+    collections::HashMap,
     fs::OpenOptions,
     io::{self, Read, Write},
+    time::{Duration, Instant, SystemTime},
 };
 use tokio::signal;
 use clap::Parser;
@@ -11,120 +13,218 @@ use dirs::cache_dir;
 use reqwest::{self, Error};
 use serde_json::Value;
 
+mod config;
+mod download;
+mod sources;
+use config::Config;
+use sources::{build_repo_entry, CacheEntry};
+
+const DEFAULT_GITHUB_DELAY: Duration = Duration::from_secs(5);
+/// How often the daemon wakes up to check whether any repo's interval has
+/// elapsed. Keeps per-repo intervals responsive without spawning a task
+/// per repo.
+const TICK: Duration = Duration::from_secs(60);
+
 /// Simple program to check GitHub releases
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// List of repositories to check, separated by commas
+    /// List of repositories to check, separated by commas. Prefix an entry
+    /// with `crates:` to watch crates.io instead of GitHub releases, e.g.
+    /// `crates:serde,github:rust-lang/rust`. Bare entries default to GitHub.
+    /// Ignored if `--config` is given.
     #[arg(short, long)]
-    repos: String,
+    repos: Option<String>,
+
+    /// TOML config file with per-repo settings (source, interval,
+    /// include_prereleases) plus global poll_interval_secs/notify_icon.
+    /// Takes precedence over `--repos`.
+    #[arg(long)]
+    config: Option<String>,
 
     /// Cache file name
     #[arg(short, long, default_value = "github-release.txt")]
     cache_file: String,
+
+    /// GitHub token for authenticated requests (5000/hour instead of
+    /// 60/hour), falls back to the GITHUB_TOKEN or GH_TOKEN env vars
+    #[arg(long)]
+    token: Option<String>,
+
+    /// Consider pre-release GitHub releases when checking for updates
+    /// (drafts are always skipped). Only applies to the `--repos` shorthand; a `--config` file
+    /// sets this per repo instead.
+    #[arg(long, default_value_t = false)]
+    include_prereleases: bool,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
     env_logger::init();
-    
+
     let args = Args::parse();
-    
-    let repos: Vec<&str> = args.repos.trim().split(',').collect();
+
+    let config = match &args.config {
+        Some(path) => config::load_config(path).expect("Failed to load config file"),
+        None => {
+            let repos = args
+                .repos
+                .as_deref()
+                .expect("Either --repos or --config must be provided");
+            config::from_repos_flag(repos, args.include_prereleases)
+        }
+    };
     let cache_file = args.cache_file;
-    println!("🐺 Got {:?}", repos);
+    let token = args
+        .token
+        .or_else(|| std::env::var("GITHUB_TOKEN").ok())
+        .or_else(|| std::env::var("GH_TOKEN").ok());
+    println!("🐺 Watching {} repo(s)", config.repos.len());
 
     let ctrl_c = signal::ctrl_c();
     tokio::select! {
-        _ = run_daemon(repos, &cache_file) => (),
+        _ = run_daemon(config, &cache_file, token.as_deref()) => (),
         _ = ctrl_c => (),
     }
 
     Ok(())
 }
 
-async fn run_daemon(repos: Vec<&str>, cache_file: &str) -> Result<(), Error> {
+async fn run_daemon(
+    config: Config,
+    cache_file: &str,
+    github_token: Option<&str>,
+) -> Result<(), Error> {
     let client = reqwest::Client::new();
-    let mut release_info = match read_cache_file(cache_file).await {
-        Ok(json) => json.as_object().unwrap().clone(),
-        Err(_) => serde_json::Map::new(),
-    };
+    let mut release_info: HashMap<String, CacheEntry> =
+        read_cache_file(cache_file).await.unwrap_or_default();
+
+    // GitHub is rate-limited much more aggressively than crates.io, so only
+    // throttle requests against it, and stretch the delay out whenever a
+    // response tells us we've exhausted our quota.
+    let mut github_delay = DEFAULT_GITHUB_DELAY;
+    // When each repo is next due to be checked, so a repo's own `interval`
+    // is honored without needing a task per repo.
+    let mut next_check: HashMap<String, Instant> = HashMap::new();
+
+    // Built once rather than per tick: these own a reqwest-independent
+    // `Box<dyn ReleaseSource>` and (for authenticated sources) a cloned
+    // token, neither of which needs to change between polls.
+    let entries: Vec<sources::RepoEntry> = config
+        .repos
+        .iter()
+        .map(|repo| match repo.source {
+            sources::SourceKind::Gitea => sources::build_gitea_entry(
+                &repo.name,
+                repo.host.as_deref().unwrap_or_default(),
+                repo.token.as_deref(),
+                repo.include_prereleases,
+            ),
+            _ => build_repo_entry(&repo.name, repo.source, github_token, repo.include_prereleases),
+        })
+        .collect();
 
     loop {
         info!("Checking for new releases");
         let mut new_release_info = serde_json::Map::new();
-        for repo in &repos {
-            let url = format!("https://api.github.com/repos/{}/releases", repo);
-            tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
-            // add user agent to avoid 403
-            let body = match client
-                .get(&url)
-                .header("User-Agent", "curl/8.11.0")
-                .send()
-                .await
-            {
-                Ok(resp) => match resp.text().await {
-                    Ok(text) => text,
-                    Err(e) => {
-                        error!("Error getting response: {}", e);
-                        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
-                        continue;
-                    }
-                },
+        let now = Instant::now();
+
+        for (repo, entry) in config.repos.iter().zip(entries.iter()) {
+            if let Some(&due) = next_check.get(&entry.key) {
+                if now < due {
+                    continue;
+                }
+            }
+            let interval = Duration::from_secs(repo.interval.unwrap_or(config.poll_interval_secs));
+            next_check.insert(entry.key.clone(), now + interval);
+
+            // Only GitHub is rate-limited aggressively enough to need the
+            // shared backoff; crates.io and self-hosted Gitea instances
+            // aren't throttled this way.
+            let is_github = entry.key.starts_with("github:");
+            let cached = release_info.get(&entry.key).cloned().unwrap_or_default();
+            // Only treat this as "seen before" once we've actually recorded a
+            // fetched tag, so a first poll that errors or finds no releases
+            // doesn't leave a `""` baseline that looks like a real change
+            // (and a spurious notification) on the next successful poll.
+            let had_previous = !cached.tag.is_empty();
+
+            if is_github {
+                tokio::time::sleep(github_delay).await;
+            }
+
+            let fetched = match entry.source.latest_version(&client, &entry.name, &cached).await {
+                Ok(fetched) => fetched,
                 Err(e) => {
-                    error!("Error sending request: {}", e);
+                    error!("Error fetching {}: {}", entry.key, e);
                     tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
                     continue;
                 }
             };
 
-            match serde_json::from_str::<Value>(&body) {
-                Ok(json) => match json.as_array() {
-                    Some(releases) => {
-                        let newest_release = releases.get(0).unwrap();
-                        if let Some(tag_name) = newest_release["tag_name"].as_str() {
-                            info!("{}: {}", repo, tag_name);
-                            if let Some(old_release) = release_info.get(&repo.to_string()) {
-                                if old_release != tag_name {
-                                    new_release_info.insert(
-                                        repo.to_string(),
-                                        Value::String(tag_name.to_string()),
-                                    );
-                                }
-                            }
-                            release_info
-                                .insert(repo.to_string(), Value::String(tag_name.to_string()));
-                        }
-                    }
-                    None => {
-                        warn!("No releases found for {}", repo);
+            if let Some(rate_limit) = fetched.rate_limit {
+                github_delay = if rate_limit.remaining == 0 {
+                    let wait = rate_limit
+                        .reset
+                        .duration_since(SystemTime::now())
+                        .unwrap_or(DEFAULT_GITHUB_DELAY);
+                    info!("Rate limit exhausted, waiting {:?} for reset", wait);
+                    wait
+                } else {
+                    DEFAULT_GITHUB_DELAY
+                };
+            }
+
+            if fetched.not_modified {
+                continue;
+            }
+
+            if let Some(tag_name) = fetched.version {
+                release_info.insert(
+                    entry.key.clone(),
+                    CacheEntry {
+                        tag: tag_name.clone(),
+                        etag: fetched.etag,
+                        last_modified: fetched.last_modified,
+                    },
+                );
+
+                info!("{}: {}", entry.key, tag_name);
+                if had_previous && sources::is_newer(&cached.tag, &tag_name) {
+                    if let Some(download_config) = &repo.download {
+                        download::download_release(
+                            &client,
+                            download_config,
+                            &fetched.assets,
+                            &entry.key,
+                        )
+                        .await;
                     }
-                },
-                Err(e) => {
-                    error!("Error parsing JSON: {}", e);
+                    new_release_info
+                        .insert(entry.key.clone(), Value::String(tag_name));
                 }
             }
         }
 
         if !new_release_info.is_empty() {
-            release_notify(new_release_info).await?;
+            release_notify(new_release_info, &config.notify_icon).await?;
         }
 
-        match write_cache_file(cache_file, &release_info).await {
-            Err(e) => error!("Error writing cache file: {}", e),
-            _ => (),
+        if let Err(e) = write_cache_file(cache_file, &release_info).await {
+            error!("Error writing cache file: {}", e);
         }
-        tokio::time::sleep(tokio::time::Duration::from_secs(3600)).await;
+        tokio::time::sleep(TICK).await;
     }
 }
 
-async fn read_cache_file(filename: &str) -> Result<Value, io::Error> {
+async fn read_cache_file(filename: &str) -> Result<HashMap<String, CacheEntry>, io::Error> {
     let cache_dir = cache_dir().expect("No cache dir found");
     let cache_file_path = cache_dir.join(filename);
     if !cache_file_path.exists() {
         let mut cache_file = OpenOptions::new()
             .write(true)
             .create(true)
+            .truncate(true)
             .open(cache_file_path.clone())
             .unwrap();
         cache_file.write_all(b"{}").unwrap();
@@ -139,29 +239,33 @@ async fn read_cache_file(filename: &str) -> Result<Value, io::Error> {
     }
     match serde_json::from_str(&cache_content) {
         Ok(json) => Ok(json),
-        Err(e) => Err(io::Error::new(io::ErrorKind::Other, e)),
+        Err(e) => Err(io::Error::other(e)),
     }
 }
 
 async fn write_cache_file(
     filename: &str,
-    json: &serde_json::Map<std::string::String, Value>,
+    cache: &HashMap<String, CacheEntry>,
 ) -> Result<(), io::Error> {
     let cache_dir = cache_dir().expect("No cache dir found");
     let cache_file_path = cache_dir.join(filename);
     let mut cache_file = OpenOptions::new()
         .write(true)
         .create(true)
+        .truncate(true)
         .open(cache_file_path)
         .unwrap();
 
-    match cache_file.write_all(serde_json::to_string(&json).unwrap().as_bytes()) {
+    match cache_file.write_all(serde_json::to_string(&cache).unwrap().as_bytes()) {
         Ok(_) => Ok(()),
         Err(e) => Err(e),
     }
 }
 
-async fn release_notify(json: serde_json::Map<std::string::String, Value>) -> Result<(), Error> {
+async fn release_notify(
+    json: serde_json::Map<std::string::String, Value>,
+    icon: &str,
+) -> Result<(), Error> {
     let mut content = String::from("");
 
     for (repo, release) in json {
@@ -170,7 +274,7 @@ async fn release_notify(json: serde_json::Map<std::string::String, Value>) -> Re
     Notification::new()
         .summary("New release")
         .body(&content)
-        .icon("librewolf")
+        .icon(icon)
         .show()
         .unwrap();
     Ok(())
@@ -0,0 +1,425 @@
+use async_trait::async_trait;
+use log::warn;
+use reqwest::{Client, Error, StatusCode};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Parses a tag like `v1.2.3` as a semver version, stripping a leading `v`.
+fn parse_semver(tag: &str) -> Option<Version> {
+    Version::parse(tag.trim_start_matches('v')).ok()
+}
+
+/// Whether `new` should be treated as newer than `old`. Compares as semver
+/// when both tags parse; falls back to plain inequality otherwise, so tags
+/// that don't follow semver still trigger a notification on change.
+pub fn is_newer(old: &str, new: &str) -> bool {
+    match (parse_semver(old), parse_semver(new)) {
+        (Some(old_version), Some(new_version)) => new_version > old_version,
+        _ => old != new,
+    }
+}
+
+/// Whether `tag` parses as semver with a non-empty pre-release identifier
+/// (e.g. `v2.0.0-rc1`). Projects frequently cut such tags without marking
+/// the GitHub release itself `prerelease: true`, so `include_prereleases`
+/// needs to check the tag's semver shape too, not just that flag.
+fn is_semver_prerelease(tag: &str) -> bool {
+    parse_semver(tag).is_some_and(|version| !version.pre.is_empty())
+}
+
+/// Conditional-request state carried over from the previous poll, so a
+/// source can send `If-None-Match`/`If-Modified-Since` instead of
+/// re-fetching and re-parsing unchanged data.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub tag: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+/// How many requests a source has left before it starts throttling itself,
+/// and when that budget resets.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub remaining: u32,
+    pub reset: SystemTime,
+}
+
+/// A downloadable file attached to a release, as reported by the source.
+#[derive(Debug, Clone)]
+pub struct Asset {
+    pub name: String,
+    pub url: String,
+    pub size: u64,
+}
+
+pub struct FetchResult {
+    /// `None` when the server reported the data is unchanged (304) or the
+    /// version couldn't be determined.
+    pub version: Option<String>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub not_modified: bool,
+    pub rate_limit: Option<RateLimit>,
+    /// Assets attached to the release that `version` refers to. Empty for
+    /// sources that don't expose assets (e.g. crates.io) or on a
+    /// not-modified response.
+    pub assets: Vec<Asset>,
+}
+
+/// A place releases can be fetched from (GitHub releases, crates.io, ...).
+#[async_trait]
+pub trait ReleaseSource {
+    /// Fetches the newest published version for `repo`, reusing `cached`'s
+    /// ETag/Last-Modified to make the request conditional when possible.
+    async fn latest_version(
+        &self,
+        client: &Client,
+        repo: &str,
+        cached: &CacheEntry,
+    ) -> Result<FetchResult, Error>;
+}
+
+fn header_str(resp: &reqwest::Response, name: &str) -> Option<String> {
+    resp.headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string())
+}
+
+fn rate_limit_from_headers(resp: &reqwest::Response) -> Option<RateLimit> {
+    let remaining = resp
+        .headers()
+        .get("x-ratelimit-remaining")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u32>().ok())?;
+    let reset_secs = resp
+        .headers()
+        .get("x-ratelimit-reset")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())?;
+    Some(RateLimit {
+        remaining,
+        reset: UNIX_EPOCH + Duration::from_secs(reset_secs),
+    })
+}
+
+/// Picks the newest non-draft release out of a GitHub-shaped releases
+/// array, and its assets. Unless `include_prereleases` is set, skips
+/// releases marked `prerelease` on GitHub as well as ones whose `tag_name`
+/// parses as semver with a pre-release identifier (e.g. `v2.0.0-rc1`),
+/// since projects don't always flag the latter on the release itself.
+/// Shared by [`GitHubSource`] and [`GiteaSource`] since Gitea/Forgejo's
+/// releases API returns the same JSON shape.
+fn pick_release(body: &str, include_prereleases: bool, repo: &str) -> (Option<String>, Vec<Asset>) {
+    let release = match serde_json::from_str::<Value>(body) {
+        Ok(json) => match json.as_array() {
+            Some(releases) => releases
+                .iter()
+                .find(|release| {
+                    !release["draft"].as_bool().unwrap_or(false)
+                        && (include_prereleases
+                            || (!release["prerelease"].as_bool().unwrap_or(false)
+                                && !release["tag_name"]
+                                    .as_str()
+                                    .is_some_and(is_semver_prerelease)))
+                })
+                .cloned(),
+            None => {
+                warn!("No releases found for {}", repo);
+                None
+            }
+        },
+        Err(e) => {
+            warn!("Error parsing JSON for {}: {}", repo, e);
+            None
+        }
+    };
+
+    let version = release
+        .as_ref()
+        .and_then(|release| release["tag_name"].as_str())
+        .map(|tag| tag.to_string());
+    let assets = release
+        .as_ref()
+        .and_then(|release| release["assets"].as_array())
+        .map(|assets| {
+            assets
+                .iter()
+                .filter_map(|asset| {
+                    Some(Asset {
+                        name: asset["name"].as_str()?.to_string(),
+                        url: asset["browser_download_url"].as_str()?.to_string(),
+                        size: asset["size"].as_u64()?,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    (version, assets)
+}
+
+pub struct GitHubSource {
+    token: Option<String>,
+    include_prereleases: bool,
+}
+
+impl GitHubSource {
+    pub fn new(token: Option<String>, include_prereleases: bool) -> Self {
+        Self {
+            token,
+            include_prereleases,
+        }
+    }
+}
+
+#[async_trait]
+impl ReleaseSource for GitHubSource {
+    async fn latest_version(
+        &self,
+        client: &Client,
+        repo: &str,
+        cached: &CacheEntry,
+    ) -> Result<FetchResult, Error> {
+        let url = format!("https://api.github.com/repos/{}/releases", repo);
+        // add user agent to avoid 403
+        let mut req = client.get(&url).header("User-Agent", "curl/8.11.0");
+        if let Some(token) = &self.token {
+            req = req.header("Authorization", format!("Bearer {}", token));
+        }
+        if let Some(etag) = &cached.etag {
+            req = req.header("If-None-Match", etag);
+        }
+        if let Some(last_modified) = &cached.last_modified {
+            req = req.header("If-Modified-Since", last_modified);
+        }
+        let resp = req.send().await?;
+        let rate_limit = rate_limit_from_headers(&resp);
+
+        if resp.status() == StatusCode::NOT_MODIFIED {
+            return Ok(FetchResult {
+                version: None,
+                etag: cached.etag.clone(),
+                last_modified: cached.last_modified.clone(),
+                not_modified: true,
+                rate_limit,
+                assets: Vec::new(),
+            });
+        }
+
+        let etag = header_str(&resp, "etag");
+        let last_modified = header_str(&resp, "last-modified");
+        let body = resp.text().await?;
+        let (version, assets) = pick_release(&body, self.include_prereleases, repo);
+
+        Ok(FetchResult {
+            version,
+            etag,
+            last_modified,
+            not_modified: false,
+            rate_limit,
+            assets,
+        })
+    }
+}
+
+pub struct CratesIoSource;
+
+#[async_trait]
+impl ReleaseSource for CratesIoSource {
+    async fn latest_version(
+        &self,
+        client: &Client,
+        name: &str,
+        cached: &CacheEntry,
+    ) -> Result<FetchResult, Error> {
+        let url = format!("https://crates.io/api/v1/crates/{}", name);
+        let mut req = client
+            .get(&url)
+            .header("User-Agent", "release_dog (https://github.com/ch3n9w/release_dog)");
+        if let Some(etag) = &cached.etag {
+            req = req.header("If-None-Match", etag);
+        }
+        if let Some(last_modified) = &cached.last_modified {
+            req = req.header("If-Modified-Since", last_modified);
+        }
+        let resp = req.send().await?;
+
+        if resp.status() == StatusCode::NOT_MODIFIED {
+            return Ok(FetchResult {
+                version: None,
+                etag: cached.etag.clone(),
+                last_modified: cached.last_modified.clone(),
+                not_modified: true,
+                rate_limit: None,
+                assets: Vec::new(),
+            });
+        }
+
+        let etag = header_str(&resp, "etag");
+        let last_modified = header_str(&resp, "last-modified");
+        let body = resp.text().await?;
+
+        let version = match serde_json::from_str::<Value>(&body) {
+            Ok(json) => json["crate"]["max_stable_version"]
+                .as_str()
+                .map(|version| version.to_string()),
+            Err(e) => {
+                warn!("Error parsing JSON for {}: {}", name, e);
+                None
+            }
+        };
+
+        Ok(FetchResult {
+            version,
+            etag,
+            last_modified,
+            not_modified: false,
+            rate_limit: None,
+            assets: Vec::new(),
+        })
+    }
+}
+
+pub struct GiteaSource {
+    base_url: String,
+    token: Option<String>,
+    include_prereleases: bool,
+}
+
+impl GiteaSource {
+    pub fn new(base_url: String, token: Option<String>, include_prereleases: bool) -> Self {
+        Self {
+            base_url,
+            token,
+            include_prereleases,
+        }
+    }
+}
+
+#[async_trait]
+impl ReleaseSource for GiteaSource {
+    async fn latest_version(
+        &self,
+        client: &Client,
+        repo: &str,
+        cached: &CacheEntry,
+    ) -> Result<FetchResult, Error> {
+        let url = format!("https://{}/api/v1/repos/{}/releases", self.base_url, repo);
+        let mut req = client.get(&url).header("User-Agent", "curl/8.11.0");
+        if let Some(token) = &self.token {
+            req = req.header("Authorization", format!("token {}", token));
+        }
+        if let Some(etag) = &cached.etag {
+            req = req.header("If-None-Match", etag);
+        }
+        if let Some(last_modified) = &cached.last_modified {
+            req = req.header("If-Modified-Since", last_modified);
+        }
+        let resp = req.send().await?;
+
+        if resp.status() == StatusCode::NOT_MODIFIED {
+            return Ok(FetchResult {
+                version: None,
+                etag: cached.etag.clone(),
+                last_modified: cached.last_modified.clone(),
+                not_modified: true,
+                rate_limit: None,
+                assets: Vec::new(),
+            });
+        }
+
+        let etag = header_str(&resp, "etag");
+        let last_modified = header_str(&resp, "last-modified");
+        let body = resp.text().await?;
+        let (version, assets) = pick_release(&body, self.include_prereleases, repo);
+
+        Ok(FetchResult {
+            version,
+            etag,
+            last_modified,
+            not_modified: false,
+            rate_limit: None,
+            assets,
+        })
+    }
+}
+
+/// Which kind of source a repo entry should be fetched from. Used both by
+/// the `--repos` shorthand prefixes (`github:`, `crates:`, `gitea:`) and by
+/// the `source` field of a TOML config entry. Gitea entries carry a
+/// `host`/`base_url` alongside `source`, since unlike GitHub/crates.io
+/// they aren't fetched from a single well-known host.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceKind {
+    #[default]
+    Github,
+    #[serde(rename = "crates.io")]
+    CratesIo,
+    Gitea,
+}
+
+/// A single repo to watch, tagged with the source it should be fetched
+/// from. `key` is the cache identifier (stable across the prefix shorthand
+/// and the TOML config), `name` is what gets passed to the source itself
+/// (`owner/repo` for GitHub, the crate name for crates.io).
+pub struct RepoEntry {
+    pub key: String,
+    pub name: String,
+    pub source: Box<dyn ReleaseSource + Send + Sync>,
+}
+
+/// Builds an entry for the GitHub or crates.io sources. Gitea entries need
+/// a host alongside `name`, so they're built with [`build_gitea_entry`]
+/// instead.
+pub fn build_repo_entry(
+    name: &str,
+    source: SourceKind,
+    github_token: Option<&str>,
+    include_prereleases: bool,
+) -> RepoEntry {
+    match source {
+        SourceKind::Github => RepoEntry {
+            key: format!("github:{}", name),
+            name: name.to_string(),
+            source: Box::new(GitHubSource::new(
+                github_token.map(String::from),
+                include_prereleases,
+            )),
+        },
+        SourceKind::CratesIo => RepoEntry {
+            key: format!("crates:{}", name),
+            name: name.to_string(),
+            source: Box::new(CratesIoSource),
+        },
+        SourceKind::Gitea => {
+            unreachable!("gitea entries are built with build_gitea_entry, which has the host")
+        }
+    }
+}
+
+/// Builds an entry for a self-hosted Gitea/Forgejo instance at `host`
+/// (e.g. `git.example.org`), optionally authenticated with a token scoped
+/// to that host.
+pub fn build_gitea_entry(
+    name: &str,
+    host: &str,
+    token: Option<&str>,
+    include_prereleases: bool,
+) -> RepoEntry {
+    RepoEntry {
+        key: format!("gitea:{}/{}", host, name),
+        name: name.to_string(),
+        source: Box::new(GiteaSource::new(
+            host.to_string(),
+            token.map(String::from),
+            include_prereleases,
+        )),
+    }
+}